@@ -0,0 +1,130 @@
+use std::sync::{Condvar, Mutex};
+use std::thread;
+
+use crate::blockchain::Block;
+
+/// Shared state for a single verification run: the next block index to hand
+/// out, how many blocks are still outstanding, and the lowest-indexed
+/// failure reported so far (if any).
+struct QueueState {
+    next_index: usize,
+    remaining: usize,
+    first_failure: Option<(usize, String)>,
+}
+
+/// Verifies `blocks` in parallel, fanning the work described by
+/// `check_block` out to a pool of `max(available_parallelism - 2, 1)`
+/// worker threads. Modeled on OpenEthereum's `BlockQueue`: workers pull the
+/// next unverified block index off a shared counter, and a condvar signals
+/// the driving thread once every block has been checked. The lowest-indexed
+/// failing block wins, so the reported error is stable no matter which
+/// worker happens to finish first.
+pub(crate) fn verify_blocks_parallel(
+    blocks: &[Block],
+    check_block: impl Fn(usize, &Block) -> Result<(), String> + Sync,
+) -> Result<(), String> {
+    if blocks.is_empty() {
+        return Ok(());
+    }
+
+    let worker_count = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .saturating_sub(2)
+        .max(1)
+        .min(blocks.len());
+
+    let state = Mutex::new(QueueState {
+        next_index: 0,
+        remaining: blocks.len(),
+        first_failure: None,
+    });
+    let done = Condvar::new();
+
+    thread::scope(|scope| {
+        for _ in 0..worker_count {
+            scope.spawn(|| loop {
+                let index = {
+                    let mut state = state.lock().unwrap();
+                    if state.next_index >= blocks.len() {
+                        break;
+                    }
+                    let index = state.next_index;
+                    state.next_index += 1;
+                    index
+                };
+
+                let result = check_block(index, &blocks[index]);
+
+                let mut state = state.lock().unwrap();
+                if let Err(err) = result {
+                    let is_lowest_so_far = state
+                        .first_failure
+                        .as_ref()
+                        .is_none_or(|(failed_index, _)| index < *failed_index);
+                    if is_lowest_so_far {
+                        state.first_failure = Some((index, err));
+                    }
+                }
+                state.remaining -= 1;
+                if state.remaining == 0 {
+                    done.notify_all();
+                }
+            });
+        }
+
+        let mut state = state.lock().unwrap();
+        while state.remaining > 0 {
+            state = done.wait(state).unwrap();
+        }
+    });
+
+    match state.into_inner().unwrap().first_failure {
+        Some((_, err)) => Err(err),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_the_error_from_a_failing_block() {
+        let blocks = vec![Block::new(None), Block::new(Some("x".into()))];
+
+        let result = verify_blocks_parallel(&blocks, |index, _block| {
+            if index == 1 {
+                Err(format!("bad block {}", index))
+            } else {
+                Ok(())
+            }
+        });
+
+        assert_eq!(result, Err("bad block 1".into()));
+    }
+
+    #[test]
+    fn lowest_failing_index_wins_regardless_of_which_worker_finishes_first() {
+        let blocks: Vec<Block> = (0..8).map(|_| Block::new(None)).collect();
+
+        for _ in 0..50 {
+            let result = verify_blocks_parallel(&blocks, |index, _block| {
+                if index == 2 {
+                    // Make the higher-indexed failure likely to finish first,
+                    // so the lowest-index-wins bookkeeping in `QueueState` is
+                    // actually exercised rather than just matching whichever
+                    // index happened to be checked first.
+                    thread::sleep(std::time::Duration::from_millis(5));
+                    return Err("failure at 2".into());
+                }
+                if index == 5 {
+                    return Err("failure at 5".into());
+                }
+                Ok(())
+            });
+
+            assert_eq!(result, Err("failure at 2".into()));
+        }
+    }
+}
@@ -1,10 +1,20 @@
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 use std::time::SystemTime;
 
 use blake2::{Blake2b, Digest};
+use ed25519_dalek::{Keypair, PublicKey, Signature, Signer, Verifier};
+use serde::{Deserialize, Serialize};
+
+use crate::block_queue::verify_blocks_parallel;
+
+/// Default proof-of-work difficulty (minimum leading zero bits a block's
+/// hash has to have) used by [`Blockchain::new`]
+const DEFAULT_DIFFICULTY: usize = 16;
 
 /// Blockchain container
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Blockchain {
     /// Store for all the blocks which are accepted
     pub blocks: Vec<Block>,
@@ -14,6 +24,17 @@ pub struct Blockchain {
 
     /// Store for transactions whick are pending in the moment.
     pending_transactions: Vec<Transaction>,
+
+    /// Stack of checkpoint layers used to undo account mutations without
+    /// cloning the whole account map. Each layer maps a touched account id
+    /// to its value right before the checkpoint was opened (`None` meaning
+    /// the account did not exist yet).
+    journal: Vec<HashMap<String, Option<Account>>>,
+
+    /// Proof-of-work difficulty: the minimum number of leading zero bits a
+    /// block's hash must have to be accepted. Exposed so callers (and
+    /// tests) can run with a low target.
+    pub difficulty: usize,
 }
 
 /// Blockchain methods
@@ -24,6 +45,22 @@ impl Blockchain {
             blocks: Vec::new(),
             accounts: HashMap::new(),
             pending_transactions: Vec::new(),
+            journal: Vec::new(),
+            difficulty: DEFAULT_DIFFICULTY,
+        }
+    }
+
+    /// Records the value of `id` from before the current (innermost)
+    /// checkpoint, the first time it is touched within that checkpoint.
+    fn journal_touch(&mut self, id: &str) {
+        let needs_recording = match self.journal.last() {
+            Some(layer) => !layer.contains_key(id),
+            None => false,
+        };
+
+        if needs_recording {
+            let prev_value = self.accounts.get(id).cloned();
+            self.journal.last_mut().unwrap().insert(id.into(), prev_value);
         }
     }
 
@@ -48,15 +85,18 @@ impl Blockchain {
             return Err("There has to be at least one transactio inside the block!".into());
         }
 
-        // TODO: refactor to something more resource friendly
-        let old_state = self.accounts.clone();
+        if !block.meets_difficulty(self.difficulty) {
+            return Err("Block has not been mined to the required difficulty".into());
+        }
+
+        self.checkpoint();
 
         // Execute each transaction and rollback if something went wrong
         for (i, transaction) in block.transactions.iter().enumerate() {
             // Execute the transaction
             if let Err(err) = transaction.execute(self, &is_genesis) {
                 // Recover state in case of fail
-                self.accounts = old_state;
+                self.revert_to_checkpoint();
 
                 // Reject current block
                 return Err(format!(
@@ -67,21 +107,26 @@ impl Blockchain {
             }
         }
 
+        self.discard_checkpoint();
         self.blocks.push(block);
 
         Ok(())
     }
+    /// Checks the whole chain is internally consistent: each block links to
+    /// its predecessor, every block's hash is correct and meets the
+    /// configured mining difficulty, every non-genesis transaction is signed,
+    /// every signed transaction carries a valid signature, and the account
+    /// map matches replaying the block history.
+    ///
+    /// The block-linking check is cheap and inherently sequential, so it
+    /// runs directly here, over the whole chain, before any per-block check.
+    /// The per-block work -- hash recomputation, difficulty, and signature
+    /// checks -- is the part that scales with chain length, so it is driven
+    /// through [`verify_blocks_parallel`] to get multicore speedup on long
+    /// chains; of its failures, the one of the lowest-indexed block is
+    /// reported.
     pub fn check_validity(&self) -> Result<(), String> {
         for (block_num, block) in self.blocks.iter().enumerate() {
-            // Check if block saved hash matches to calculated hash
-            if !block.verify_own_hash() {
-                return Err(format!(
-                    "Stored hash for Block #{} \
-                    does not match calculated hash",
-                    block_num + 1
-                ));
-            }
-
             // Check previous black hash points to actual previous block
             if block_num == 0 {
                 // Genesis block should point to nowhere
@@ -108,10 +153,40 @@ impl Blockchain {
                     ));
                 }
             }
+        }
+
+        verify_blocks_parallel(&self.blocks, |block_num, block| {
+            // Check if block saved hash matches to calculated hash
+            if !block.verify_own_hash() {
+                return Err(format!(
+                    "Stored hash for Block #{} \
+                    does not match calculated hash",
+                    block_num + 1
+                ));
+            }
+
+            // Check block was actually mined to the configured difficulty
+            if !block.meets_difficulty(self.difficulty) {
+                return Err(format!(
+                    "Block #{} does not meet the required proof-of-work difficulty",
+                    block_num + 1
+                ));
+            }
 
-            // Check if transactions are signed correctly
+            // Check if transactions are signed correctly. Every non-genesis
+            // transaction has to carry a signature at all (not just a valid
+            // one if present), otherwise a hand-crafted chain could smuggle
+            // in an unsigned, spoofed transfer and still pass this check.
             for (transaction_num, transaction) in block.transactions.iter().enumerate() {
-                if transaction.is_signed() && !transaction.check_signature() {
+                if block_num != 0 && !transaction.is_signed() {
+                    return Err(format!(
+                        "Transaction #{} for Block #{} is missing a required signature",
+                        transaction_num + 1,
+                        block_num + 1
+                    ));
+                }
+
+                if transaction.is_signed() && !transaction.check_signature(self) {
                     return Err(format!(
                         "Transaction #{} for Block #{} has an invalid signature",
                         transaction_num + 1,
@@ -119,13 +194,89 @@ impl Blockchain {
                     ));
                 }
             }
+
+            Ok(())
+        })?;
+
+        // The per-block checks above only look at `self.blocks`; they say
+        // nothing about whether `self.accounts` is actually what you get by
+        // replaying that block history. Re-derive it from scratch and
+        // compare, so tampering with account balances directly (without
+        // touching a single block or signature) is caught too.
+        let derived_accounts = self.derive_accounts_from_blocks()?;
+        if derived_accounts != self.accounts {
+            return Err(
+                "Account state does not match replaying the block history".into(),
+            );
         }
+
         Ok(())
     }
+
+    /// Rebuilds the account map from scratch by replaying every transaction
+    /// in block order against a fresh chain.
+    ///
+    /// Called after [`verify_blocks_parallel`] has already checked every
+    /// transaction's signature, so instructions are applied via
+    /// [`Transaction::execute_trusted`] rather than `execute`: re-verifying
+    /// signatures here would redo that work single-threaded on top of the
+    /// parallel pass.
+    fn derive_accounts_from_blocks(&self) -> Result<HashMap<String, Account>, String> {
+        let mut replay = Blockchain::new();
+
+        for (block_num, block) in self.blocks.iter().enumerate() {
+            let is_genesis = block_num == 0;
+
+            for (transaction_num, transaction) in block.transactions.iter().enumerate() {
+                transaction
+                    .execute_trusted(&mut replay, &is_genesis)
+                    .map_err(|err| {
+                        format!(
+                            "Transaction #{} for Block #{} could not be replayed: {}",
+                            transaction_num + 1,
+                            block_num + 1,
+                            err
+                        )
+                    })?;
+            }
+        }
+
+        Ok(replay.accounts)
+    }
+
+    /// Serializes the chain with bincode and writes it to `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let encoded =
+            bincode::serialize(self).map_err(|err| format!("Could not serialize chain: {}", err))?;
+
+        fs::write(path, encoded).map_err(|err| format!("Could not write chain to disk: {}", err))
+    }
+
+    /// Reads a chain previously written by [`Blockchain::save`]. Does not
+    /// panic on a truncated or tampered file: deserialization failures and
+    /// `check_validity` failures both surface as a descriptive `Err` rather
+    /// than a bad in-memory chain.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let bytes =
+            fs::read(path).map_err(|err| format!("Could not read chain file: {}", err))?;
+
+        let chain: Blockchain = bincode::deserialize(&bytes)
+            .map_err(|err| format!("Chain file is corrupted: {}", err))?;
+
+        chain.check_validity()?;
+
+        Ok(chain)
+    }
+}
+
+impl Default for Blockchain {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Block
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub hash: Option<String>,
     pub prev_hash: Option<String>,
@@ -144,10 +295,24 @@ impl Block {
         }
     }
 
-    /// Changes the unqnum number and updates the hash
-    pub fn set_unqnum(&mut self, unqnum: i128) {
-        self.unqnum = unqnum;
-        self.update_hash();
+    /// Mines the block: repeatedly increments `unqnum` (used here as a
+    /// proof-of-work nonce) and updates the hash until `calculate_hash()`
+    /// has at least `difficulty` leading zero bits, giving the block a real
+    /// cost to produce.
+    pub fn mine(&mut self, difficulty: usize) {
+        loop {
+            self.update_hash();
+            if self.meets_difficulty(difficulty) {
+                break;
+            }
+            self.unqnum += 1;
+        }
+    }
+
+    /// Returns true if this block's hash has at least `difficulty` leading
+    /// zero bits.
+    pub fn meets_difficulty(&self, difficulty: usize) -> bool {
+        has_leading_zero_bits(&self.calculate_hash(), difficulty)
     }
 
     /// Will calculate the hash of the whole block including transactions Blake2 hasher
@@ -196,7 +361,7 @@ impl Block {
     }
 }
 /// Transaction
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     /// Unique number
     unqnum: u128,
@@ -207,42 +372,114 @@ pub struct Transaction {
     /// Time the transaction was created
     created_at: SystemTime,
 
-    /// Transaction type and it's information
-    pub(crate) record: TransactionData,
+    /// Ordered list of instructions this transaction executes as a single
+    /// indivisible unit: either all of them apply, or none do.
+    pub(crate) record: Vec<TransactionData>,
 
-    /// Signature of the message (basic auth)
-    signature: Option<String>,
+    /// Detached ed25519 signature over `calculate_hash()`
+    signature: Option<Signature>,
 }
 
 impl Transaction {
-    pub fn new(from: String, transaction_data: TransactionData, unqnum: u128) -> Self {
+    pub fn new(from: String, instructions: Vec<TransactionData>, unqnum: u128) -> Self {
         Transaction {
             from,
             unqnum,
-            record: transaction_data,
+            record: instructions,
             created_at: SystemTime::now(),
             signature: None,
         }
     }
 
     /// Will change the world state according to the transactions commands
+    ///
+    /// Instructions run in order within their own checkpoint: if instruction
+    /// `k` fails, the partial effects of instructions `0..k` are undone
+    /// before the error propagates, so the whole transaction commits or none
+    /// of it does.
     pub fn execute<T: WorldState>(
         &self,
         world_state: &mut T,
         is_initial: &bool,
+    ) -> Result<(), &'static str> {
+        self.execute_inner(world_state, is_initial, true)
+    }
+
+    /// Like [`Transaction::execute`], but skips re-verifying the signature.
+    ///
+    /// For replaying a chain whose blocks have already had their signatures
+    /// checked (e.g. by [`verify_blocks_parallel`] inside `check_validity`),
+    /// re-running `check_signature` here would just pay the same ed25519
+    /// verification cost again, single-threaded, on top of the parallel pass.
+    pub(crate) fn execute_trusted<T: WorldState>(
+        &self,
+        world_state: &mut T,
+        is_initial: &bool,
+    ) -> Result<(), &'static str> {
+        self.execute_inner(world_state, is_initial, false)
+    }
+
+    fn execute_inner<T: WorldState>(
+        &self,
+        world_state: &mut T,
+        is_initial: &bool,
+        verify_signature: bool,
     ) -> Result<(), &'static str> {
         // Check if sending user does exist (no one not on the chain can execute transactions)
-        if let Some(_account) = world_state.get_account_by_id(&self.from) {
-            // Do some more checkups later on...
-        } else if !is_initial {
+        if world_state.get_account_by_id(&self.from).is_none() && !is_initial {
             return Err("Account does not exist");
         }
 
+        // Every non-genesis transaction has to carry a valid signature from the
+        // account it claims to be `from`, otherwise anyone could spoof someone
+        // else's account id and move their tokens.
+        if !is_initial && verify_signature && !self.check_signature(world_state) {
+            return Err("Transaction has no valid signature");
+        }
+
+        world_state.checkpoint();
+
+        for instruction in self.record.iter() {
+            // `CreateTokens` is the only instruction allowed to mint new
+            // supply; every other instruction has to leave the total token
+            // supply across all accounts unchanged.
+            let conserves_supply = !matches!(instruction, TransactionData::CreateTokens { .. });
+            let supply_before = if conserves_supply {
+                Some(total_token_supply(world_state))
+            } else {
+                None
+            };
+
+            if let Err(err) = self.execute_instruction(instruction, world_state, is_initial) {
+                world_state.revert_to_checkpoint();
+                return Err(err);
+            }
+
+            if let Some(supply_before) = supply_before {
+                if total_token_supply(world_state) != supply_before {
+                    world_state.revert_to_checkpoint();
+                    return Err("Transaction would not conserve the total token supply");
+                }
+            }
+        }
+
+        world_state.discard_checkpoint();
+        Ok(())
+    }
+
+    /// Runs a single instruction from this transaction's instruction list
+    /// against the world state.
+    fn execute_instruction<T: WorldState>(
+        &self,
+        instruction: &TransactionData,
+        world_state: &mut T,
+        is_initial: &bool,
+    ) -> Result<(), &'static str> {
         // match is like a switch (pattern matching) in C++ or Java
         // We will check for the type of transaction here and execute its logic
-        match &self.record {
-            TransactionData::CreateUserAccount(account) => {
-                world_state.create_account(account.into(), AccountType::User)
+        match instruction {
+            TransactionData::CreateUserAccount { id, public_key } => {
+                world_state.create_account(id.into(), AccountType::User, *public_key)
             }
 
             TransactionData::CreateTokens { receiver, amount } => {
@@ -259,6 +496,19 @@ impl Transaction {
             }
 
             TransactionData::TransferTokens { to, amount } => {
+                // Transferring to yourself never changes the balance. Handle
+                // it up front: the generic path below takes two mutable
+                // borrows of the same account when `to == from`, and its
+                // second write would silently overwrite (and so mint, via)
+                // the first.
+                if to == &self.from {
+                    return match world_state.get_account_by_id(&self.from) {
+                        Some(account) if account.tokens >= *amount => Ok(()),
+                        Some(_) => Err("Overspent or Arithmetic error"),
+                        None => Err("That account does not exist!"),
+                    };
+                }
+
                 let recv_tokens: u128;
                 let sender_tokens: u128;
 
@@ -278,48 +528,90 @@ impl Transaction {
                 let balance_recv_new = recv_tokens.checked_add(*amount);
                 let balance_sender_new = sender_tokens.checked_sub(*amount);
 
-                if balance_recv_new.is_some() && balance_sender_new.is_some() {
-                    world_state
-                        .get_account_by_id_mut(&self.from)
-                        .unwrap()
-                        .tokens = balance_sender_new.unwrap();
-                    world_state.get_account_by_id_mut(to).unwrap().tokens =
-                        balance_recv_new.unwrap();
-                    Ok(())
-                } else {
-                    Err("Overspent or Arithmetic error")
+                match (balance_recv_new, balance_sender_new) {
+                    (Some(new_recv), Some(new_sender)) => {
+                        world_state.get_account_by_id_mut(&self.from).unwrap().tokens =
+                            new_sender;
+                        world_state.get_account_by_id_mut(to).unwrap().tokens = new_recv;
+                        Ok(())
+                    }
+                    _ => Err("Overspent or Arithmetic error"),
                 }
             }
 
-            _ => {
-                // Not implemented transaction type
-                Err("Unknown Transaction type (not implemented)")
+            TransactionData::ChangeStoreValue { key, value } => {
+                // An account can only ever write to its own store: we always
+                // resolve the target through `self.from`, which `execute`
+                // has already required a valid signature for, so there is no
+                // way to reach another account's `store` from here.
+                if let Some(account) = world_state.get_account_by_id_mut(&self.from) {
+                    account.store.insert(key.clone(), value.clone());
+                    Ok(())
+                } else {
+                    Err("Account does not exist")
+                }
             }
         }
     }
 
-    /// Will calculate the hash using Blake2 hasher
+    /// Will calculate the hash using Blake2 hasher, folding over every
+    /// instruction in the transaction's instruction list
     pub fn calculate_hash(&self) -> Vec<u8> {
         let mut hasher = Blake2b::new();
-        let transaction_as_string = format!(
-            "{:?}",
-            (&self.created_at, &self.record, &self.from, &self.unqnum)
-        );
+
+        for instruction in self.record.iter() {
+            // `CreateUserAccount` carries an `ed25519_dalek::PublicKey`, whose
+            // derived `Debug` output embeds a non-canonical internal curve
+            // point representation that can differ between two equal keys
+            // (e.g. a freshly generated one vs. one reconstructed by
+            // deserializing). Hashing the canonical serialized bytes instead
+            // keeps this stable across a `save`/`load` round trip.
+            let instruction_bytes =
+                bincode::serialize(instruction).expect("TransactionData is always serializable");
+            hasher.update(&instruction_bytes);
+        }
+
+        let transaction_as_string =
+            format!("{:?}", (&self.created_at, &self.from, &self.unqnum));
 
         hasher.update(&transaction_as_string);
         Vec::from(hasher.finalize().as_ref())
     }
 
+    /// Signs the transaction with the given keypair, storing the detached
+    /// signature over `calculate_hash()` in the `signature` field.
+    pub fn sign(&mut self, keypair: &Keypair) {
+        let hash = self.calculate_hash();
+        self.signature = Some(keypair.sign(&hash));
+    }
+
     /// Will hash the transaction and check if the signature is valid
     /// (i.e., it is created by the owners private key)
     /// if the message is not signed it will always return false
-    pub fn check_signature(&self) -> bool {
-        if !(self.is_signed()) {
-            return false;
-        }
+    pub fn check_signature<T: WorldState>(&self, world_state: &T) -> bool {
+        let signature = match &self.signature {
+            Some(signature) => signature,
+            None => return false,
+        };
+
+        // The signature has to come from the key of the account already on
+        // chain named in `from`, which is what stops someone from spoofing
+        // another user's `from` field. Only while that account does not exist
+        // yet, and a leading `CreateUserAccount` registers it under that same
+        // id, is the key the transaction itself is registering used instead.
+        let public_key = match world_state.get_account_by_id(&self.from) {
+            Some(account) => account.public_key,
+            None => match self.record.first() {
+                Some(TransactionData::CreateUserAccount { id, public_key }) if id == &self.from => {
+                    *public_key
+                }
+                _ => return false,
+            },
+        };
 
-        //@TODO check signature
-        false
+        public_key
+            .verify(&self.calculate_hash(), signature)
+            .is_ok()
     }
 
     pub fn is_signed(&self) -> bool {
@@ -327,10 +619,11 @@ impl Transaction {
     }
 }
 /// TransactionData
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TransactionData {
-    /// Store for new user account
-    CreateUserAccount(String),
+    /// Store for new user account, along with the public key that will
+    /// have to sign every future transaction sent `from` that account
+    CreateUserAccount { id: String, public_key: PublicKey },
 
     /// Method for changing or creating value into an account
     ChangeStoreValue { key: String, value: String },
@@ -343,7 +636,7 @@ pub enum TransactionData {
 }
 
 /// Account
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Account {
     /// Store for user's data
     store: HashMap<String, String>,
@@ -353,21 +646,25 @@ pub struct Account {
 
     /// Amount of tokens
     tokens: u128,
+
+    /// Public key transactions `from` this account have to be signed with
+    public_key: PublicKey,
 }
 
 /// Account methods
 impl Account {
     /// Constructor
-    pub fn new(account_type: AccountType) -> Self {
+    pub fn new(account_type: AccountType, public_key: PublicKey) -> Self {
         Self {
             tokens: 0,
             account_type,
             store: HashMap::new(),
+            public_key,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 // TODO: implement more types such as Validator(to check validation of blocks in the chain)
 /// Account type
 pub enum AccountType {
@@ -385,19 +682,49 @@ pub trait WorldState {
 
     fn get_account_by_id(&self, id: &str) -> Option<&Account>;
 
+    /// Method for reading a single key out of an account's program store
+    fn get_store_value(&self, id: &str, key: &str) -> Option<&String>;
+
     /// Method for adding a new account
-    fn create_account(&mut self, id: String, account_type: AccountType)
-        -> Result<(), &'static str>;
+    fn create_account(
+        &mut self,
+        id: String,
+        account_type: AccountType,
+        public_key: PublicKey,
+    ) -> Result<(), &'static str>;
+
+    /// Opens a new checkpoint layer on the journal stack. Every account
+    /// touched after this call has its pre-checkpoint value recorded the
+    /// first time it is touched, modeled on OpenEthereum's `State`
+    /// checkpoints.
+    fn checkpoint(&mut self);
+
+    /// Pops the top journal layer, restoring every account it recorded to
+    /// its value (or absence) from before the checkpoint was opened; each
+    /// account is recorded once per layer, so the order does not matter. O(accounts touched) rather than O(all accounts).
+    fn revert_to_checkpoint(&mut self);
+
+    /// Merges the top journal layer into the one below it, keeping only the
+    /// oldest recorded value per account so an outer checkpoint can still be
+    /// reverted all the way back. Drops the layer entirely if it was the
+    /// outermost checkpoint.
+    fn discard_checkpoint(&mut self);
 }
 
 impl WorldState for Blockchain {
     fn get_account_by_id_mut(&mut self, id: &str) -> Option<&mut Account> {
+        self.journal_touch(id);
         self.accounts.get_mut(id)
     }
 
     fn get_account_by_id(&self, id: &str) -> Option<&Account> {
         self.accounts.get(id)
     }
+
+    fn get_store_value(&self, id: &str, key: &str) -> Option<&String> {
+        self.accounts.get(id)?.store.get(key)
+    }
+
     fn get_user_ids(&self) -> Vec<String> {
         self.accounts.keys().cloned().collect()
     }
@@ -406,17 +733,317 @@ impl WorldState for Blockchain {
         &mut self,
         id: String,
         account_type: AccountType,
+        public_key: PublicKey,
     ) -> Result<(), &'static str> {
         if !self.get_user_ids().contains(&id) {
-            let acc = Account::new(account_type);
+            self.journal_touch(&id);
+            let acc = Account::new(account_type, public_key);
             self.accounts.insert(id, acc);
             Ok(())
         } else {
             Err("User already exists!")
         }
     }
+
+    fn checkpoint(&mut self) {
+        self.journal.push(HashMap::new());
+    }
+
+    fn revert_to_checkpoint(&mut self) {
+        if let Some(layer) = self.journal.pop() {
+            for (id, prev_value) in layer {
+                match prev_value {
+                    Some(account) => {
+                        self.accounts.insert(id, account);
+                    }
+                    None => {
+                        self.accounts.remove(&id);
+                    }
+                }
+            }
+        }
+    }
+
+    fn discard_checkpoint(&mut self) {
+        if let Some(layer) = self.journal.pop() {
+            if let Some(below) = self.journal.last_mut() {
+                for (id, prev_value) in layer {
+                    below.entry(id).or_insert(prev_value);
+                }
+            }
+        }
+    }
 }
 
 fn byte_vector_to_string(arr: &[u8]) -> String {
-    arr.iter().map(|&c| c as char).collect()
+    hex::encode(arr)
+}
+
+/// Sums the token balance of every account known to `world_state`.
+fn total_token_supply<T: WorldState>(world_state: &T) -> u128 {
+    world_state
+        .get_user_ids()
+        .iter()
+        .filter_map(|id| world_state.get_account_by_id(id))
+        .map(|account| account.tokens)
+        .sum()
+}
+
+/// Returns true if `hash` has at least `difficulty` leading zero bits.
+fn has_leading_zero_bits(hash: &[u8], difficulty: usize) -> bool {
+    let mut remaining_bits = difficulty;
+
+    for byte in hash {
+        if remaining_bits == 0 {
+            return true;
+        }
+
+        if remaining_bits >= 8 {
+            if *byte != 0 {
+                return false;
+            }
+            remaining_bits -= 8;
+        } else {
+            return byte.leading_zeros() as usize >= remaining_bits;
+        }
+    }
+
+    remaining_bits == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    /// Builds a one-account, one-block chain (account created and funded in
+    /// the genesis block) with a low mining difficulty so tests stay fast.
+    fn make_single_user_chain() -> (Blockchain, Keypair) {
+        let mut chain = Blockchain::new();
+        chain.difficulty = 1;
+
+        let keypair = Keypair::generate(&mut OsRng);
+
+        let mut genesis = Block::new(None);
+        let mut create_and_fund = Transaction::new(
+            "Alice".into(),
+            vec![
+                TransactionData::CreateUserAccount {
+                    id: "Alice".into(),
+                    public_key: keypair.public,
+                },
+                TransactionData::CreateTokens {
+                    receiver: "Alice".into(),
+                    amount: 1_000,
+                },
+            ],
+            0,
+        );
+        create_and_fund.sign(&keypair);
+        genesis.add_transaction(create_and_fund);
+        genesis.mine(chain.difficulty);
+
+        chain.append_block(genesis).unwrap();
+
+        (chain, keypair)
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let (chain, _keypair) = make_single_user_chain();
+
+        let path = std::env::temp_dir().join(format!("rustchain-test-{}.bin", std::process::id()));
+        chain.save(&path).unwrap();
+        let loaded = Blockchain::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded.accounts, chain.accounts);
+        assert_eq!(loaded.blocks.len(), chain.blocks.len());
+    }
+
+    #[test]
+    fn check_validity_detects_balance_tampering_outside_block_history() {
+        let (mut chain, _keypair) = make_single_user_chain();
+
+        // No block or signature is touched here, only the in-memory account
+        // map, which `check_validity` must not trust blindly.
+        chain.accounts.get_mut("Alice").unwrap().tokens = 999_999_999;
+
+        assert!(chain.check_validity().is_err());
+    }
+
+    #[test]
+    fn check_validity_rejects_unsigned_non_genesis_transaction() {
+        let (mut chain, _keypair) = make_single_user_chain();
+
+        let mut block2 = Block::new(chain.get_last_block_hash());
+        block2.add_transaction(Transaction::new(
+            "Alice".into(),
+            vec![TransactionData::TransferTokens {
+                to: "Alice".into(),
+                amount: 1,
+            }],
+            0,
+        ));
+        block2.mine(chain.difficulty);
+
+        // Bypass `append_block` (which would already reject this) to mimic a
+        // hand-crafted or corrupted chain file reaching `check_validity`
+        // directly, e.g. via `load`.
+        chain.blocks.push(block2);
+
+        assert!(chain.check_validity().is_err());
+    }
+
+    #[test]
+    fn change_store_value_write_is_readable_via_get_store_value() {
+        let (mut chain, keypair) = make_single_user_chain();
+
+        let mut set_greeting = Transaction::new(
+            "Alice".into(),
+            vec![TransactionData::ChangeStoreValue {
+                key: "greeting".into(),
+                value: "hello".into(),
+            }],
+            0,
+        );
+        set_greeting.sign(&keypair);
+
+        let mut block2 = Block::new(chain.get_last_block_hash());
+        block2.add_transaction(set_greeting);
+        block2.mine(chain.difficulty);
+
+        chain.append_block(block2).unwrap();
+
+        assert_eq!(
+            chain.get_store_value("Alice", "greeting"),
+            Some(&"hello".to_string())
+        );
+    }
+
+    #[test]
+    fn failed_block_leaves_accounts_unchanged() {
+        let (mut chain, keypair) = make_single_user_chain();
+        let accounts_before = chain.accounts.clone();
+
+        let mut valid_tx = Transaction::new(
+            "Alice".into(),
+            vec![TransactionData::ChangeStoreValue {
+                key: "greeting".into(),
+                value: "hello".into(),
+            }],
+            0,
+        );
+        valid_tx.sign(&keypair);
+
+        // References a nonexistent account, so this fails and the whole
+        // block -- including the otherwise-valid transaction before it --
+        // must be rolled back via the block-level checkpoint.
+        let mut bad_tx = Transaction::new(
+            "Alice".into(),
+            vec![TransactionData::TransferTokens {
+                to: "Nobody".into(),
+                amount: 1,
+            }],
+            1,
+        );
+        bad_tx.sign(&keypair);
+
+        let mut block2 = Block::new(chain.get_last_block_hash());
+        block2.add_transaction(valid_tx);
+        block2.add_transaction(bad_tx);
+        block2.mine(chain.difficulty);
+
+        assert!(chain.append_block(block2).is_err());
+        assert_eq!(chain.accounts, accounts_before);
+    }
+
+    #[test]
+    fn instruction_failure_rolls_back_earlier_instructions_in_same_transaction() {
+        let (mut chain, keypair) = make_single_user_chain();
+
+        let mut tx = Transaction::new(
+            "Alice".into(),
+            vec![
+                TransactionData::ChangeStoreValue {
+                    key: "greeting".into(),
+                    value: "hello".into(),
+                },
+                // CreateTokens is only allowed during genesis, so this fails
+                // and must undo the ChangeStoreValue write above within this
+                // same transaction's checkpoint.
+                TransactionData::CreateTokens {
+                    receiver: "Alice".into(),
+                    amount: 1,
+                },
+            ],
+            0,
+        );
+        tx.sign(&keypair);
+
+        let mut block2 = Block::new(chain.get_last_block_hash());
+        block2.add_transaction(tx);
+        block2.mine(chain.difficulty);
+
+        assert!(chain.append_block(block2).is_err());
+        assert_eq!(chain.get_store_value("Alice", "greeting"), None);
+    }
+
+    #[test]
+    fn self_transfer_does_not_mint_tokens() {
+        let (mut chain, keypair) = make_single_user_chain();
+
+        let mut tx = Transaction::new(
+            "Alice".into(),
+            vec![TransactionData::TransferTokens {
+                to: "Alice".into(),
+                amount: 100,
+            }],
+            0,
+        );
+        tx.sign(&keypair);
+
+        let mut block2 = Block::new(chain.get_last_block_hash());
+        block2.add_transaction(tx);
+        block2.mine(chain.difficulty);
+
+        chain.append_block(block2).unwrap();
+
+        assert_eq!(chain.accounts["Alice"].tokens, 1_000);
+    }
+
+    #[test]
+    fn unmined_block_is_rejected() {
+        let mut chain = Blockchain::new();
+        // High enough that an unmined block won't meet it by chance.
+        chain.difficulty = 32;
+
+        let keypair = Keypair::generate(&mut OsRng);
+        let mut genesis = Block::new(None);
+        let mut create_and_fund = Transaction::new(
+            "Alice".into(),
+            vec![
+                TransactionData::CreateUserAccount {
+                    id: "Alice".into(),
+                    public_key: keypair.public,
+                },
+                TransactionData::CreateTokens {
+                    receiver: "Alice".into(),
+                    amount: 1_000,
+                },
+            ],
+            0,
+        );
+        create_and_fund.sign(&keypair);
+        genesis.add_transaction(create_and_fund);
+        // Deliberately never mined: the hash is set, but no nonce search was
+        // done to make it meet `chain.difficulty`.
+
+        assert!(chain.append_block(genesis.clone()).is_err());
+
+        // Bypass append_block to mimic an unmined block reaching
+        // check_validity directly, e.g. via load.
+        chain.blocks.push(genesis);
+        assert!(chain.check_validity().is_err());
+    }
 }
@@ -1,5 +1,10 @@
 use std::borrow::BorrowMut;
+use std::collections::HashMap;
 
+use ed25519_dalek::Keypair;
+use rand::rngs::OsRng;
+
+mod block_queue;
 mod blockchain;
 use blockchain::{Block, Blockchain, Transaction, TransactionData};
 
@@ -12,31 +17,36 @@ fn main() {
     let mut genesis = Block::new(None);
 
     let initial_users = vec!["John", "Mereep"];
+    let mut keypairs: HashMap<&str, Keypair> = HashMap::new();
 
     for user in initial_users {
-        let uniq_num = Transaction::generate_unqnum();
-
-        println!("UNIQ NUMBER: {}", uniq_num);
+        let keypair = Keypair::generate(&mut OsRng);
 
-        let create_transaction = Transaction::new(
+        // Create the account and fund it as a single atomic transaction: either
+        // both instructions land, or neither does.
+        let mut create_and_fund = Transaction::new(
             user.into(),
-            TransactionData::CreateUserAccount(user.into()),
-            uniq_num, // 0,
+            vec![
+                TransactionData::CreateUserAccount {
+                    id: user.into(),
+                    public_key: keypair.public,
+                },
+                TransactionData::CreateTokens {
+                    receiver: user.into(),
+                    amount: 10_000,
+                },
+            ],
+            0,
         );
+        create_and_fund.sign(&keypair);
 
-        let token_action = Transaction::new(
-            user.into(),
-            TransactionData::CreateTokens {
-                receiver: user.into(),
-                amount: 10_000,
-            },
-            uniq_num, // 0,
-        );
+        genesis.add_transaction(create_and_fund);
 
-        genesis.add_transaction(create_transaction);
-        genesis.add_transaction(token_action);
+        keypairs.insert(user, keypair);
     }
 
+    genesis.mine(chain.difficulty);
+
     let mut res = chain.append_block(genesis);
     println!("Genesis block was added: {:?}", res);
     println!("Full blockchain: ");
@@ -44,31 +54,33 @@ fn main() {
 
     // Transfer 100 tokens from John to Mereep
     let mut block2 = Block::new(chain.get_last_block_hash());
-    block2.add_transaction(Transaction::new(
+    let mut transfer_john_mereep = Transaction::new(
         "John".into(),
-        TransactionData::TransferTokens {
+        vec![TransactionData::TransferTokens {
             to: "Mereep".into(),
             amount: 100,
-        },
+        }],
         0,
-    ));
-    // FIXME: do something about dummy 0 in Block::unqnum
-    block2.set_unqnum(324);
+    );
+    transfer_john_mereep.sign(&keypairs["John"]);
+    block2.add_transaction(transfer_john_mereep);
+    block2.mine(chain.difficulty);
 
     res = chain.append_block(block2);
     println!("Block2 added: {:?}", res);
 
     let mut block3 = Block::new(chain.get_last_block_hash());
-    block3.add_transaction(Transaction::new(
+    let mut transfer_mereep_john = Transaction::new(
         "Mereep".into(),
-        TransactionData::TransferTokens {
+        vec![TransactionData::TransferTokens {
             to: "John".into(),
             amount: 1000,
-        },
+        }],
         0,
-    ));
-
-    block3.set_unqnum(95);
+    );
+    transfer_mereep_john.sign(&keypairs["Mereep"]);
+    block3.add_transaction(transfer_mereep_john);
+    block3.mine(chain.difficulty);
 
     res = chain.append_block(block3);
     println!("Block3 added: {:?}", res);
@@ -81,11 +93,11 @@ fn main() {
 
     let transaction_data = chain_attack.blocks[1].transactions[0].borrow_mut();
 
-    // Change the amount value of the transaction inside the chain
+    // Change the amount value of the first instruction inside the transaction
     if let TransactionData::TransferTokens {
         to: _,
         ref mut amount,
-    } = transaction_data.record.borrow_mut()
+    } = transaction_data.record[0].borrow_mut()
     {
         *amount = 1000; // Changing the value in place
     }